@@ -0,0 +1,37 @@
+//! Small helpers that assert common account invariants before the program
+//! trusts an account: that it is rent-exempt and owned by the expected
+//! program. They mirror the token-vault utilities in the Metaplex programs,
+//! and let Anker's check methods fail with a precise [`AnkerError`] instead of
+//! a subtle error further down the call. (Token accounts are unpacked with the
+//! Token-2022 extension layout in `check_is_spl_token_account`, which asserts
+//! initialization and the not-frozen state there.)
+
+use crate::error::AnkerError;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, pubkey::Pubkey, rent::Rent,
+};
+
+/// Assert that `account_info` holds enough lamports to be rent-exempt.
+pub fn assert_rent_exempt(rent: &Rent, account_info: &AccountInfo) -> ProgramResult {
+    if !rent.is_exempt(account_info.lamports(), account_info.data_len()) {
+        msg!("Account {} is not rent exempt.", account_info.key);
+        Err(AnkerError::AccountNotRentExempt.into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Assert that `account_info` is owned by `owner`.
+pub fn assert_owned_by(account_info: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    if account_info.owner != owner {
+        msg!(
+            "Account {} is owned by {}, but expected owner {}.",
+            account_info.key,
+            account_info.owner,
+            owner,
+        );
+        Err(AnkerError::AccountWrongOwner.into())
+    } else {
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::assertions::{assert_owned_by, assert_rent_exempt};
 use crate::instruction::SellRewardsAccountsInfo;
 use crate::{
     error::AnkerError, ANKER_MINT_AUTHORITY, ANKER_RESERVE_AUTHORITY, ANKER_STSOL_RESERVE_ACCOUNT,
@@ -7,12 +8,107 @@ use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
 use lido::state::Lido;
 use lido::util::serialize_b58;
 use serde::Serialize;
+use std::convert::TryFrom;
 use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_pack::Pack, pubkey::Pubkey,
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    program_pack::Pack, pubkey::Pubkey, rent::Rent, sysvar::Sysvar,
 };
+use spl_token_2022::extension::{
+    non_transferable::NonTransferable, transfer_fee::TransferFeeConfig, BaseStateWithExtensions,
+    StateWithExtensions,
+};
+
+/// Maximum number of approved token-swap pools an Anker instance can whitelist.
+pub const MAX_APPROVED_POOLS: usize = 8;
+
+/// Serialize an array of `Pubkey` as base58 strings, like [`serialize_b58`].
+fn serialize_b58_array<S: serde::Serializer>(
+    pubkeys: &[Pubkey],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeSeq;
+    let mut seq = serializer.serialize_seq(Some(pubkeys.len()))?;
+    for pubkey in pubkeys {
+        seq.serialize_element(&pubkey.to_string())?;
+    }
+    seq.end()
+}
 
-/// Size of the serialized [`Anker`] struct, in bytes.
-pub const ANKER_LEN: usize = 166;
+/// Size of the Borsh-serialized [`Anker`] struct, without the version tag.
+const ANKER_BODY_LEN: usize = 270 + 1 + MAX_APPROVED_POOLS * 32;
+
+/// Size of the original, untagged `Anker` layout, as written by instances
+/// created before the versioning and Token-2022 work. Used only by
+/// [`Anker::upgrade_from_unversioned`] to read an old account before rewriting
+/// it in the current tagged form.
+const ANKER_V0_BODY_LEN: usize = 5 * 32 + 6;
+
+/// Basis-point denominator used for the slippage bound.
+const BASIS_POINTS_DENOMINATOR: u64 = 10_000;
+
+/// Serialized length of a versioned Anker account: a one-byte version tag
+/// followed by the Borsh-serialized [`Anker`] struct.
+pub const ANKER_LEN: usize = 1 + ANKER_BODY_LEN;
+
+/// Version tag written ahead of a serialized [`Anker`].
+///
+/// Accounts carry this byte so the on-chain layout can grow new fields without
+/// breaking existing instances, mirroring the way the token-swap program tags
+/// `SwapV1` accounts behind an `Unallocated`/`Init` discriminator.
+pub const ANKER_VERSION: u8 = 1;
+
+/// Versioned wrapper around [`Anker`].
+///
+/// Only `V1` exists today; deserialization dispatches on the leading tag byte
+/// and rejects any version this program does not know how to read.
+pub enum AnkerVersion {
+    V1(Anker),
+}
+
+impl AnkerVersion {
+    /// Write the version tag followed by the Borsh-serialized body.
+    pub fn save(&self, account: &AccountInfo) -> ProgramResult {
+        let AnkerVersion::V1(anker) = self;
+        let mut data = account.data.borrow_mut();
+        data[0] = ANKER_VERSION;
+        BorshSerialize::serialize(anker, &mut &mut data[1..])?;
+        Ok(())
+    }
+
+    /// Read a versioned Anker account, dispatching on the leading tag byte.
+    pub fn deserialize(data: &[u8]) -> Result<AnkerVersion, ProgramError> {
+        match data.split_first() {
+            Some((&ANKER_VERSION, body)) => {
+                let anker = Anker::try_from_slice(&body[..ANKER_BODY_LEN])?;
+                Ok(AnkerVersion::V1(anker))
+            }
+            Some((&version, _)) => {
+                msg!("Unknown Anker account version {}.", version);
+                Err(AnkerError::UnsupportedAnkerVersion.into())
+            }
+            None => Err(AnkerError::UnsupportedAnkerVersion.into()),
+        }
+    }
+}
+
+/// Confirm that `program_id` is one of the token programs Anker understands.
+///
+/// Anker accepts both the legacy SPL Token program and Token-2022, so that a
+/// single instruction can work with mints and token accounts that expose the
+/// same token interface under either program.
+fn check_is_token_program(program_id: &Pubkey) -> ProgramResult {
+    if program_id == &spl_token::id() || program_id == &spl_token_2022::id() {
+        Ok(())
+    } else {
+        msg!(
+            "Expected a supported token program ({} or {}), but found {}.",
+            spl_token::id(),
+            spl_token_2022::id(),
+            program_id,
+        );
+        Err(AnkerError::UnsupportedTokenProgram.into())
+    }
+}
 
 #[repr(C)]
 #[derive(
@@ -31,6 +127,18 @@ pub struct Anker {
     #[serde(serialize_with = "serialize_b58")]
     pub b_sol_mint: Pubkey,
 
+    /// Token program that owns the bSOL mint (legacy SPL Token or Token-2022).
+    #[serde(serialize_with = "serialize_b58")]
+    pub b_sol_token_program_id: Pubkey,
+
+    /// Token program that owns the stSOL reserve (legacy SPL Token or Token-2022).
+    #[serde(serialize_with = "serialize_b58")]
+    pub st_sol_token_program_id: Pubkey,
+
+    /// Token program that owns the UST reserve (legacy SPL Token or Token-2022).
+    #[serde(serialize_with = "serialize_b58")]
+    pub ust_token_program_id: Pubkey,
+
     /// Token swap data. Used to swap stSOL for UST.
     #[serde(serialize_with = "serialize_b58")]
     pub pool: Pubkey,
@@ -56,6 +164,50 @@ pub struct Anker {
 
     /// Bump seed for the Token Swap.
     pub token_swap_bump_seed: u8,
+
+    /// Maximum slippage tolerated when selling stSOL rewards for UST.
+    ///
+    /// Expressed in basis points of the output that the pool's own reserves and
+    /// fee schedule say the swap should realize. A maintainer-supplied
+    /// `minimum_out` that is looser than this bound is rejected, so a swap can
+    /// never be front-run into an unfavourable fill.
+    pub max_swap_slippage_bps: u64,
+
+    /// Number of entries in `approved_pools` that are in use.
+    pub num_approved_pools: u8,
+
+    /// Fixed-capacity whitelist of additional approved token-swap pools.
+    ///
+    /// A SellRewards transaction may route through the legacy `pool` (the
+    /// implicit entry zero, always approved) or any of the first
+    /// `num_approved_pools` entries here, which lets an off-chain maintainer
+    /// pick whichever approved pool currently offers the best stSOL→UST price
+    /// instead of being pinned to a single, possibly illiquid market. Each
+    /// SellRewards transaction supplies the chosen pool together with a
+    /// `minimum_out` bound (see [`Anker::check_token_swap`]).
+    #[serde(serialize_with = "serialize_b58_array")]
+    pub approved_pools: [Pubkey; MAX_APPROVED_POOLS],
+}
+
+/// The original, untagged on-chain layout of [`Anker`], kept only so that
+/// accounts created before versioning can be migrated into the current form.
+///
+/// The field order must match the old layout byte-for-byte; new fields added
+/// since (token program ids, slippage bound, approved-pool whitelist) are not
+/// present here and are filled with defaults on upgrade.
+#[derive(BorshDeserialize, BorshSerialize)]
+struct AnkerV0 {
+    solido_program_id: Pubkey,
+    solido: Pubkey,
+    b_sol_mint: Pubkey,
+    pool: Pubkey,
+    rewards_destination: Pubkey,
+    self_bump_seed: u8,
+    mint_authority_bump_seed: u8,
+    reserve_authority_bump_seed: u8,
+    stsol_reserve_account_bump_seed: u8,
+    ust_reserve_account_bump_seed: u8,
+    token_swap_bump_seed: u8,
 }
 
 impl Anker {
@@ -64,8 +216,55 @@ impl Anker {
         // runtime complained that an account's size was modified by a program
         // that wasn't its owner, double check that the name passed to
         // ProgramTest matches the name of the crate.
-        BorshSerialize::serialize(self, &mut *account.data.borrow_mut())?;
-        Ok(())
+        AnkerVersion::V1(self.clone()).save(account)
+    }
+
+    /// Read an [`Anker`] from a versioned account, rejecting unknown versions.
+    pub fn deserialize(data: &[u8]) -> Result<Anker, ProgramError> {
+        let AnkerVersion::V1(anker) = AnkerVersion::deserialize(data)?;
+        Ok(anker)
+    }
+
+    /// Migrate an account written in the old, untagged fixed layout into the
+    /// current tagged form, so instances created before versioning can gain new
+    /// fields without a hard fork.
+    pub fn upgrade_from_unversioned(account: &AccountInfo) -> ProgramResult {
+        // Read the *old* fixed layout. The old account has no version tag and is
+        // `ANKER_V0_BODY_LEN` bytes long, so parsing the new, larger struct out
+        // of it would fail (or read garbage); we map the old fields explicitly
+        // and default the ones introduced since.
+        let old = {
+            let data = account.data.borrow();
+            AnkerV0::try_from_slice(&data[..ANKER_V0_BODY_LEN])?
+        };
+
+        let anker = Anker {
+            solido_program_id: old.solido_program_id,
+            solido: old.solido,
+            b_sol_mint: old.b_sol_mint,
+            // Pre-upgrade instances only used the legacy SPL Token program.
+            b_sol_token_program_id: spl_token::id(),
+            st_sol_token_program_id: spl_token::id(),
+            ust_token_program_id: spl_token::id(),
+            pool: old.pool,
+            rewards_destination: old.rewards_destination,
+            self_bump_seed: old.self_bump_seed,
+            mint_authority_bump_seed: old.mint_authority_bump_seed,
+            reserve_authority_bump_seed: old.reserve_authority_bump_seed,
+            stsol_reserve_account_bump_seed: old.stsol_reserve_account_bump_seed,
+            ust_reserve_account_bump_seed: old.ust_reserve_account_bump_seed,
+            token_swap_bump_seed: old.token_swap_bump_seed,
+            max_swap_slippage_bps: 0,
+            // Keep the legacy `pool` as the implicit entry zero of the
+            // whitelist so reward sales keep working after the upgrade.
+            num_approved_pools: 0,
+            approved_pools: [Pubkey::default(); MAX_APPROVED_POOLS],
+        };
+
+        // The tagged layout is larger than the old one, so grow the account
+        // before writing the new body.
+        account.realloc(ANKER_LEN, false)?;
+        anker.save(account)
     }
 
     /// Confirm that the account address is the derived address where the Anker instance should live.
@@ -195,15 +394,15 @@ impl Anker {
     }
 
     /// Confirm that the provided mint account is the one stored in this instance.
+    ///
+    /// The mint may be owned by either the legacy SPL Token program or
+    /// Token-2022. A Token-2022 mint is rejected if it carries a transfer-fee
+    /// or non-transferable extension, because either would break the reward
+    /// accounting that assumes transfers move the full amount freely.
     pub fn check_mint(&self, provided_mint: &AccountInfo) -> ProgramResult {
-        if *provided_mint.owner != spl_token::id() {
-            msg!(
-                "Expected bSOL mint to be owned by the SPL token program ({}), but found {}.",
-                spl_token::id(),
-                provided_mint.owner,
-            );
-            return Err(AnkerError::InvalidTokenMint.into());
-        }
+        check_is_token_program(provided_mint.owner)?;
+        assert_owned_by(provided_mint, &self.b_sol_token_program_id)?;
+        assert_rent_exempt(&Rent::get()?, provided_mint)?;
 
         if self.b_sol_mint != *provided_mint.key {
             msg!(
@@ -213,26 +412,45 @@ impl Anker {
             );
             return Err(AnkerError::InvalidTokenMint.into());
         }
+
+        // Token-2022 mints can carry extensions; reject the ones that would
+        // silently change how many tokens a transfer actually delivers.
+        let mint_data = provided_mint.data.borrow();
+        let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)
+            .map_err(|_| AnkerError::InvalidTokenMint)?;
+        if mint.get_extension::<TransferFeeConfig>().is_ok()
+            || mint.get_extension::<NonTransferable>().is_ok()
+        {
+            msg!(
+                "The bSOL mint {} carries a transfer-fee or non-transferable extension, which Anker does not support.",
+                provided_mint.key,
+            );
+            return Err(AnkerError::UnsupportedMintExtension.into());
+        }
+
         Ok(())
     }
 
+    /// Confirm that `token_account_info` is a token account owned by
+    /// `expected_program_id` and holding the given mint.
+    ///
+    /// The account is unpacked with the Token-2022 layout, which reads the base
+    /// account of both programs and tolerates the trailing TLV extension data
+    /// that Token-2022 accounts may carry.
     fn check_is_spl_token_account(
         mint_name: &'static str,
         mint_address: &Pubkey,
+        expected_program_id: &Pubkey,
         token_account_info: &AccountInfo,
     ) -> ProgramResult {
-        if token_account_info.owner != &spl_token::id() {
-            msg!(
-                "Expected SPL token account to be owned by {}, but it's owned by {} instead.",
-                spl_token::id(),
-                token_account_info.owner
-            );
-            return Err(AnkerError::InvalidTokenAccountOwner.into());
-        }
+        check_is_token_program(expected_program_id)?;
+        assert_owned_by(token_account_info, expected_program_id)?;
+        assert_rent_exempt(&Rent::get()?, token_account_info)?;
 
+        let account_data = token_account_info.data.borrow();
         let token_account =
-            match spl_token::state::Account::unpack_from_slice(&token_account_info.data.borrow()) {
-                Ok(account) => account,
+            match StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account_data) {
+                Ok(state) => state.base,
                 Err(..) => {
                     msg!(
                         "Expected an SPL token account at {}.",
@@ -253,12 +471,29 @@ impl Anker {
             return Err(AnkerError::InvalidTokenMint.into());
         }
 
+        // The account must be initialized and not frozen, otherwise transfers
+        // in or out of it can fail or be blocked, breaking reward accounting.
+        use spl_token_2022::state::AccountState;
+        if token_account.state == AccountState::Uninitialized {
+            msg!("Token account {} is not initialized.", token_account_info.key);
+            return Err(AnkerError::AccountNotInitialized.into());
+        }
+        if token_account.state == AccountState::Frozen {
+            msg!("Token account {} is frozen.", token_account_info.key);
+            return Err(AnkerError::FrozenTokenAccount.into());
+        }
+
         Ok(())
     }
 
     /// Confirm that the account is an SPL token account that holds bSOL.
     pub fn check_is_b_sol_account(&self, token_account_info: &AccountInfo) -> ProgramResult {
-        Anker::check_is_spl_token_account("our bSOL", &self.b_sol_mint, token_account_info)
+        Anker::check_is_spl_token_account(
+            "our bSOL",
+            &self.b_sol_mint,
+            &self.b_sol_token_program_id,
+            token_account_info,
+        )
     }
 
     /// Confirm that the account is an SPL token account that holds stSOL.
@@ -267,7 +502,12 @@ impl Anker {
         solido: &Lido,
         token_account_info: &AccountInfo,
     ) -> ProgramResult {
-        Anker::check_is_spl_token_account("Solido's stSOL", &solido.st_sol_mint, token_account_info)
+        Anker::check_is_spl_token_account(
+            "Solido's stSOL",
+            &solido.st_sol_mint,
+            &self.st_sol_token_program_id,
+            token_account_info,
+        )
     }
 
     /// Check the if the token swap program is the same as the one stored in the
@@ -275,23 +515,47 @@ impl Anker {
     ///
     /// Check all the token swap associated accounts.
     /// Check if the rewards destination is the same as the one stored in Anker.
+    ///
+    /// `amount_in` is the amount of stSOL that SellRewards is about to sell, and
+    /// `minimum_out` is the maintainer-supplied lower bound on the UST received,
+    /// carried per transaction alongside the chosen approved pool so that
+    /// best-price routing can never be filled below the caller's expectation.
+    /// Once all the pool accounts have been validated, the realizable output is
+    /// priced from the pool's reserves and checked against both `minimum_out`
+    /// and the stored slippage bound, so a mispriced or malicious pool cannot
+    /// drain the stSOL reserve for near-zero UST.
     pub fn check_token_swap(
         &self,
         anker_program_id: &Pubkey,
         accounts: &SellRewardsAccountsInfo,
+        amount_in: u64,
+        minimum_out: u64,
     ) -> ProgramResult {
-        // Check token swap instance parameters.
-        if &self.pool != accounts.pool.key {
+        // Check that the chosen pool is one of the approved ones. The maintainer
+        // selects per transaction whichever approved pool currently offers the
+        // best price; any pool not on the whitelist is rejected.
+        if self.find_approved_pool(accounts.pool.key).is_none() {
             msg!(
-                "Invalid Token Swap instance, expected {}, found {}",
-                self.pool,
-                accounts.pool.key
+                "Token Swap instance {} is not in the approved-pool whitelist.",
+                accounts.pool.key,
             );
             return Err(AnkerError::WrongSplTokenSwap.into());
         }
         // We should ignore the 1st byte for the unpack.
         let token_swap = spl_token_swap::state::SwapV1::unpack(&accounts.pool.data.borrow()[1..])?;
 
+        // Anker can only price constant-product pools, which have the simple
+        // closed form used by `expected_ust_out`. Refuse any other curve rather
+        // than authorize a swap whose output we cannot bound.
+        if token_swap.swap_curve.curve_type != spl_token_swap::curve::base::CurveType::ConstantProduct
+        {
+            msg!(
+                "Token Swap pool uses curve {:?}, but Anker can only price constant-product pools.",
+                token_swap.swap_curve.curve_type,
+            );
+            return Err(AnkerError::UnsupportedSwapCurve.into());
+        }
+
         // Check UST token accounts.
         self.check_ust_reserve_address(anker_program_id, accounts.anker.key, accounts.ust_token)?;
 
@@ -363,10 +627,178 @@ impl Anker {
             return Err(AnkerError::InvalidRewardsDestination.into());
         }
 
+        // Economic safety check. The field checks above have confirmed that
+        // `token_a`/`token_b` are the stSOL/UST reserves in that order, so the
+        // closed-form pricing in `expected_ust_out` sees the reserves the right
+        // way round. Read both reserve balances and enforce the slippage bound.
+        let st_sol_pool_balance = unpack_token_amount(accounts.st_sol_token)?;
+        let ust_pool_balance = unpack_token_amount(accounts.ust_token)?;
+        self.check_swap_slippage(
+            amount_in,
+            st_sol_pool_balance,
+            ust_pool_balance,
+            &token_swap,
+            minimum_out,
+        )?;
+
+        Ok(())
+    }
+
+    /// Return the index of `pool` in the approved whitelist, if present.
+    ///
+    /// The legacy `pool` field is the implicit entry zero and is always
+    /// approved, so instances that predate the whitelist (and migrated
+    /// accounts, whose `num_approved_pools` is zero) keep working without an
+    /// admin first populating the array.
+    pub fn find_approved_pool(&self, pool: &Pubkey) -> Option<usize> {
+        if pool == &self.pool {
+            return Some(0);
+        }
+        self.approved_pools[..self.num_approved_pools as usize]
+            .iter()
+            .position(|approved| approved == pool)
+            .map(|index| index + 1)
+    }
+
+    /// Add `pool` to the whitelist. Admin-only; the processor is expected to
+    /// have verified the manager's signature before calling this.
+    pub fn add_approved_pool(&mut self, pool: Pubkey) -> ProgramResult {
+        if self.find_approved_pool(&pool).is_some() {
+            // Adding an already-approved pool is a no-op rather than an error.
+            return Ok(());
+        }
+        let index = self.num_approved_pools as usize;
+        if index >= MAX_APPROVED_POOLS {
+            msg!("The approved-pool whitelist is full ({} pools).", MAX_APPROVED_POOLS);
+            return Err(AnkerError::PoolWhitelistFull.into());
+        }
+        self.approved_pools[index] = pool;
+        self.num_approved_pools += 1;
+        Ok(())
+    }
+
+    /// Remove `pool` from the whitelist, keeping the remaining entries packed.
+    ///
+    /// The legacy `pool` is the implicit entry zero and cannot be removed here.
+    pub fn remove_approved_pool(&mut self, pool: &Pubkey) -> ProgramResult {
+        let index = match self.approved_pools[..self.num_approved_pools as usize]
+            .iter()
+            .position(|approved| approved == pool)
+        {
+            Some(index) => index,
+            None => {
+                msg!("Pool {} is not in the approved-pool whitelist.", pool);
+                return Err(AnkerError::PoolNotApproved.into());
+            }
+        };
+        let last = self.num_approved_pools as usize - 1;
+        self.approved_pools[index] = self.approved_pools[last];
+        self.approved_pools[last] = Pubkey::default();
+        self.num_approved_pools -= 1;
+        Ok(())
+    }
+
+    /// Confirm that selling `amount_in` stSOL into the pool would realize at
+    /// least `minimum_out` UST, and that `minimum_out` is itself no looser than
+    /// the stored `max_swap_slippage_bps` relative to the pool's own price.
+    ///
+    /// `st_sol_pool_balance` and `ust_pool_balance` are the pool's token A and
+    /// token B reserves; the expected output follows the constant-product
+    /// closed form after deducting the pool's trade fee.
+    pub fn check_swap_slippage(
+        &self,
+        amount_in: u64,
+        st_sol_pool_balance: u64,
+        ust_pool_balance: u64,
+        token_swap: &spl_token_swap::state::SwapV1,
+        minimum_out: u64,
+    ) -> ProgramResult {
+        // A slippage bound looser than 100% is nonsensical and would saturate
+        // the floor computation below to zero, so reject it outright.
+        if self.max_swap_slippage_bps > BASIS_POINTS_DENOMINATOR {
+            msg!(
+                "Configured max slippage {} bps exceeds the {} bps maximum.",
+                self.max_swap_slippage_bps,
+                BASIS_POINTS_DENOMINATOR,
+            );
+            return Err(AnkerError::InvalidSlippageBound.into());
+        }
+
+        let expected_out = expected_ust_out(
+            amount_in,
+            st_sol_pool_balance,
+            ust_pool_balance,
+            &token_swap.fees,
+        )
+        .ok_or(AnkerError::SlippageExceeded)?;
+
+        // The realizable output must clear the caller's bound, ...
+        if expected_out < minimum_out {
+            msg!(
+                "Swap would return {} UST, but the minimum acceptable is {}.",
+                expected_out,
+                minimum_out,
+            );
+            return Err(AnkerError::SlippageExceeded.into());
+        }
+
+        // ... and the caller's bound must itself be tight enough: at least
+        // `expected_out * (1 - max_swap_slippage_bps)`, so a maintainer cannot
+        // pass a near-zero `minimum_out` that would let the pool drain the
+        // reserve for almost nothing.
+        let floor = (expected_out as u128)
+            .saturating_mul(
+                BASIS_POINTS_DENOMINATOR.saturating_sub(self.max_swap_slippage_bps) as u128,
+            )
+            / BASIS_POINTS_DENOMINATOR as u128;
+        if (minimum_out as u128) < floor {
+            msg!(
+                "Minimum out {} is looser than the allowed slippage of {} bps (floor {}).",
+                minimum_out,
+                self.max_swap_slippage_bps,
+                floor,
+            );
+            return Err(AnkerError::SlippageExceeded.into());
+        }
+
         Ok(())
     }
 }
 
+/// Read the token balance of `token_account_info`, tolerating the trailing TLV
+/// extension data that Token-2022 accounts may carry.
+fn unpack_token_amount(token_account_info: &AccountInfo) -> Result<u64, ProgramError> {
+    let data = token_account_info.data.borrow();
+    let account = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)
+        .map_err(|_| AnkerError::InvalidTokenAccount)?;
+    Ok(account.base.amount)
+}
+
+/// UST output of a constant-product pool for `amount_in` stSOL, after the
+/// pool's trade fee: `out = b_reserve * dx_after_fee / (a_reserve + dx_after_fee)`.
+///
+/// Returns `None` on arithmetic overflow or an empty pool, so the caller treats
+/// an unpriceable swap as slippage-exceeded rather than authorizing it.
+fn expected_ust_out(
+    amount_in: u64,
+    a_reserve: u64,
+    b_reserve: u64,
+    fees: &spl_token_swap::curve::fees::Fees,
+) -> Option<u64> {
+    let trade_fee = fees.trading_fee(amount_in as u128)?;
+    let owner_fee = fees.owner_trading_fee(amount_in as u128)?;
+    let dx_after_fee = (amount_in as u128)
+        .checked_sub(trade_fee)?
+        .checked_sub(owner_fee)?;
+
+    let numerator = (b_reserve as u128).checked_mul(dx_after_fee)?;
+    let denominator = (a_reserve as u128).checked_add(dx_after_fee)?;
+    if a_reserve == 0 || b_reserve == 0 || denominator == 0 {
+        return None;
+    }
+    u64::try_from(numerator / denominator).ok()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -376,6 +808,8 @@ mod test {
         let instance = Anker::default();
         let mut writer = Vec::new();
         BorshSerialize::serialize(&instance, &mut writer).unwrap();
-        assert_eq!(writer.len(), ANKER_LEN);
+        // The serialized body is stored after the one-byte version tag.
+        assert_eq!(writer.len() + 1, ANKER_LEN);
+        assert_eq!(writer.len(), ANKER_BODY_LEN);
     }
 }
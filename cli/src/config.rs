@@ -2,25 +2,95 @@ use clap::Clap;
 use serde::Deserialize;
 use serde_json::Value;
 use solana_sdk::pubkey::{ParsePubkeyError, Pubkey};
-use std::{path::PathBuf, str::FromStr};
+use std::{convert::TryFrom, path::PathBuf, str::FromStr};
 
-pub fn get_option_from_config<T: FromStr>(
+/// Resolve a value of type `Self` from a `serde_json::Value` in the config file.
+///
+/// This is the config-file counterpart of [`FromStr`]: it lets a field be read
+/// from JSON regardless of whether the underlying value is a string, a number,
+/// a boolean, or an array, instead of requiring everything to be spelled as a
+/// string. The error is a human-readable description of what went wrong, to be
+/// surfaced to the user.
+pub trait FromConfigValue: Sized {
+    fn from_config_value(value: &Value) -> Result<Self, String>;
+}
+
+impl FromConfigValue for String {
+    fn from_config_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::String(str_value) => Ok(str_value.clone()),
+            _ => Err(format!("expected a string, found {}", value)),
+        }
+    }
+}
+
+impl FromConfigValue for Pubkey {
+    fn from_config_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::String(str_value) => Pubkey::from_str(str_value)
+                .map_err(|err| format!("invalid address {}: {}", str_value, err)),
+            _ => Err(format!("expected an address string, found {}", value)),
+        }
+    }
+}
+
+/// Implement [`FromConfigValue`] for an integer type read from `Value::Number`.
+macro_rules! impl_from_config_value_int {
+    ($type:ty, $method:ident) => {
+        impl FromConfigValue for $type {
+            fn from_config_value(value: &Value) -> Result<Self, String> {
+                match value {
+                    Value::Number(num) => num
+                        .$method()
+                        .and_then(|n| <$type>::try_from(n).ok())
+                        .ok_or_else(|| {
+                            format!("{} is not a valid {}", num, stringify!($type))
+                        }),
+                    _ => Err(format!("expected a number, found {}", value)),
+                }
+            }
+        }
+    };
+}
+
+impl_from_config_value_int!(u32, as_u64);
+impl_from_config_value_int!(u64, as_u64);
+impl_from_config_value_int!(i32, as_i64);
+
+impl FromConfigValue for bool {
+    fn from_config_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(format!("expected a boolean, found {}", value)),
+        }
+    }
+}
+
+/// Read a JSON array, resolving every element through [`FromConfigValue`].
+impl<T: FromConfigValue> FromConfigValue for Vec<T> {
+    fn from_config_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Array(elements) => elements
+                .iter()
+                .map(T::from_config_value)
+                .collect::<Result<Vec<T>, String>>(),
+            _ => Err(format!("expected an array, found {}", value)),
+        }
+    }
+}
+
+pub fn get_option_from_config<T: FromConfigValue>(
     name: &'static str,
     config_file: Option<&ConfigFile>,
 ) -> Option<T> {
     let config_file = config_file?;
     let value = config_file.values.get(name)?;
-    if let Value::String(str_value) = value {
-        match T::from_str(str_value) {
-            Err(_) => {
-                eprintln!("Could not convert {} from string", str_value);
-                std::process::exit(1);
-            }
-            Ok(pubkey) => Some(pubkey),
+    match T::from_config_value(value) {
+        Err(err) => {
+            eprintln!("Could not read key {} from config file: {}", name, err);
+            std::process::exit(1);
         }
-    } else {
-        // TODO: Support numbers
-        None
+        Ok(value) => Some(value),
     }
 }
 /// Generates a struct that derives `Clap` for usage with a config file.
@@ -66,7 +136,7 @@ pub fn get_option_from_config<T: FromStr>(
 /// When `merge_with_config(config_file)` is called, if the `foo` field has a
 /// value (set by passing `--foo-arg <pubkey>`) it does nothing, otherwise,
 /// search `config_file` for the key 'foo_arg' and sets the field accordingly.
-/// The type must implement the `FromStr` trait.
+/// The type must implement the `FromConfigValue` trait.
 /// In the example, `def_arg` will have value 3 if not present in the config file.
 
 macro_rules! cli_opt_struct {
@@ -130,7 +200,6 @@ macro_rules! cli_opt_struct {
 }
 
 /// Type to represent a vector of `Pubkey`.
-// TODO(#218) Accept an array in the json config file.
 #[derive(Debug, Clone)]
 pub struct PubkeyVec(pub Vec<Pubkey>);
 /// Constructs a `PubkeyVec` from a string by splitting the string by ',' and
@@ -146,6 +215,17 @@ impl FromStr for PubkeyVec {
         Ok(PubkeyVec(pubkeys))
     }
 }
+/// Reads a `PubkeyVec` from a JSON array `["pk1", "pk2"]`, and for backward
+/// compatibility still accepts a single comma-separated string.
+impl FromConfigValue for PubkeyVec {
+    fn from_config_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::String(str_value) => PubkeyVec::from_str(str_value)
+                .map_err(|err| format!("invalid comma-separated address list: {}", err)),
+            _ => Vec::<Pubkey>::from_config_value(value).map(PubkeyVec),
+        }
+    }
+}
 
 #[derive(Debug, Deserialize)]
 pub struct ConfigFile {